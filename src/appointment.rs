@@ -0,0 +1,54 @@
+//! Types describing the appointments (lessons) returned by the Zermelo API.
+
+/// A single appointment (lesson, exam, or other calendar item) on a schedule.
+///
+/// Most fields are optional because Zermelo only returns the fields that are relevant to the
+/// appointment's type and the requesting user's permissions.
+///
+/// Deserialized directly from the API's camelCase JSON via `#[serde(rename_all = "camelCase")]`,
+/// rather than through a lossy string-substitution pre-pass.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Appointment {
+    /// The unique id of this appointment.
+    pub id: Option<i64>,
+    /// The id shared by all appointments that belong to the same lesson instance.
+    pub appointment_instance: Option<i64>,
+    /// The start time of the appointment, as a Unix timestamp.
+    pub start: Option<i64>,
+    /// The end time of the appointment, as a Unix timestamp.
+    pub end: Option<i64>,
+    /// The index of the time slot the appointment starts in.
+    pub start_time_slot: Option<i64>,
+    /// The index of the time slot the appointment ends in.
+    pub end_time_slot: Option<i64>,
+    /// The subjects taught during this appointment.
+    pub subjects: Option<Vec<String>>,
+    /// The teachers giving this appointment.
+    pub teachers: Option<Vec<String>>,
+    /// The groups (classes) attending this appointment.
+    pub groups: Option<Vec<String>>,
+    /// The locations (rooms) this appointment takes place in.
+    pub locations: Option<Vec<String>>,
+    /// The type of the appointment, e.g. `"lesson"` or `"exam"`.
+    #[serde(rename = "type")]
+    pub appointment_type: Option<String>,
+    /// A remark added to the appointment, e.g. instructions for students.
+    pub remark: Option<String>,
+    /// Whether the appointment is considered valid by Zermelo.
+    pub valid: Option<bool>,
+    /// Whether the appointment has been cancelled.
+    pub cancelled: Option<bool>,
+    /// Whether the appointment has been modified since its original scheduling.
+    pub modified: Option<bool>,
+    /// Whether the appointment has been moved to a different time slot.
+    pub moved: Option<bool>,
+    /// Whether the appointment is newly added to the schedule.
+    pub new: Option<bool>,
+    /// A human-readable description of the most recent change, if any.
+    pub change_description: Option<String>,
+    /// The Unix timestamp of the last modification to this appointment.
+    pub last_modified: Option<i64>,
+    /// The id of the branch of the school this appointment belongs to.
+    pub branch_of_school: Option<i64>,
+}
@@ -0,0 +1,312 @@
+//! An asynchronous, non-blocking counterpart to [`Schedule`](../schedule/struct.Schedule.html),
+//! built on top of `reqwest`'s `r#async` client so this crate can be used from within an
+//! async runtime (Tokio/async-std) without blocking a thread per request.
+
+use std::error::Error;
+use std::time::{Duration, SystemTime};
+
+use futures::future::{self, Either, Loop};
+use futures::Future;
+
+use appointment::Appointment;
+use client::{AsyncScheduleClient, ScheduleClient};
+use schedule::{
+    AppointmentsResponse, AppointmentsResponseResponse, AuthenticationResponse, ScheduleError,
+    TokenInfoResponse,
+};
+use stream::{ChangeStream, ChangeStreamConfig};
+
+/// An async variant of `Schedule`, using a single, reusable
+/// [`AsyncScheduleClient`](../client/struct.AsyncScheduleClient.html) instead of constructing a
+/// client per request.
+///
+/// `Clone` shares the `Arc`-backed `reqwest::r#async::Client` and copies the `String` fields and
+/// the `appointments` vector (empty until `get_appointments` has run), and is what lets [`ChangeStream`](../stream/struct.ChangeStream.html) drive repeated, independent
+/// fetches without holding a borrow across `poll` calls.
+#[derive(Clone)]
+pub struct AsyncSchedule {
+    /// The school id used in the URL.
+    pub school: String,
+    /// The access token obtained from the API, used to obtain appointments.
+    pub access_token: String,
+    /// A vector of the appointments.
+    pub appointments: Vec<Appointment>,
+    /// The total number of rows the API reported for the most recent `get_appointments` call,
+    /// or `None` if no call has been made yet.
+    pub total_rows: Option<i64>,
+    /// The token type reported alongside `access_token`, e.g. `"bearer"`. `None` if the access
+    /// token was supplied directly through `with_access_token` rather than obtained from the
+    /// API.
+    pub token_type: Option<String>,
+    /// The absolute time at which `access_token` expires, if known.
+    pub expires_at: Option<SystemTime>,
+    client: AsyncScheduleClient,
+}
+
+impl AsyncSchedule {
+    /// Create a new `AsyncSchedule` from an authorization code (only once usable) and a school
+    /// identifier, using a default `AsyncScheduleClient`. This will get the access token from
+    /// the API. Returns a future resolving to a `AsyncSchedule`, or an error.
+    pub fn new<S>(school: &S, code: &S) -> impl Future<Item = Self, Error = Box<Error>>
+    where
+        S: ToString,
+    {
+        let client = ScheduleClient::builder()
+            .build_async()
+            .expect("the default ScheduleClient is always valid");
+        Self::with_client(school, code, client)
+    }
+
+    /// Create a new `AsyncSchedule` from an authorization code (only once usable), a school
+    /// identifier, and a pre-built `AsyncScheduleClient`. This will get the access token from
+    /// the API. Returns a future resolving to a `AsyncSchedule`, or an error.
+    pub fn with_client<S>(
+        school: &S,
+        code: &S,
+        client: AsyncScheduleClient,
+    ) -> impl Future<Item = Self, Error = Box<Error>>
+    where
+        S: ToString,
+    {
+        let school = school.to_string();
+        // Remove spaces from code.
+        let code = code.to_string().replace(" ", "");
+
+        let url = client.url(&school, "/api/v3/oauth/token");
+        let post_data = [("grant_type", "authorization_code"), ("code", code.as_str())];
+
+        let school_for_future = school.clone();
+        let client_for_future = client.clone();
+
+        client
+            .inner
+            .post(url.as_str())
+            .form(&post_data)
+            .send()
+            .map_err(|err| Box::new(err) as Box<Error>)
+            .and_then(|response| {
+                if response.status().as_u16() != 200 {
+                    return Err(Box::new(ScheduleError("response code is not 200".to_string())) as Box<Error>);
+                }
+                Ok(response)
+            })
+            .and_then(move |mut response| {
+                response
+                    .json()
+                    .map_err(|err| Box::new(err) as Box<Error>)
+                    .map(move |json: AuthenticationResponse| {
+                        let expires_at = json
+                            .expires_in
+                            .map(|expires_in| SystemTime::now() + Duration::from_secs(expires_in));
+
+                        AsyncSchedule {
+                            school: school_for_future,
+                            access_token: json.access_token,
+                            appointments: Vec::new(),
+                            total_rows: None,
+                            token_type: json.token_type,
+                            expires_at,
+                            client: client_for_future,
+                        }
+                    })
+            })
+    }
+
+    /// Create a new `AsyncSchedule` when an access token has been obtained already, using a
+    /// default `AsyncScheduleClient`. This cannot fail, so this will not return a `Result`.
+    pub fn with_access_token<S>(school: &S, access_token: &S) -> Self
+    where
+        S: ToString,
+    {
+        AsyncSchedule {
+            school: school.to_string(),
+            access_token: access_token.to_string(),
+            appointments: Vec::new(),
+            total_rows: None,
+            token_type: None,
+            expires_at: None,
+            client: ScheduleClient::builder()
+                .build_async()
+                .expect("the default ScheduleClient is always valid"),
+        }
+    }
+
+    /// Whether `self.access_token` has expired, based on the expiry reported when it was
+    /// obtained. Returns `false` if no expiry is known (e.g. the token was supplied directly
+    /// through `with_access_token`).
+    pub fn is_expired(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => SystemTime::now() >= expires_at,
+            None => false,
+        }
+    }
+
+    /// Ask the API whether `self.access_token` is still valid, via the token introspection
+    /// endpoint.
+    pub fn introspect_token(&self) -> impl Future<Item = bool, Error = Box<Error>> {
+        let url = format!(
+            "{}?access_token={}",
+            self.client.url(&self.school, "/api/v3/oauth/token/info"),
+            self.access_token
+        );
+
+        self.client
+            .inner
+            .get(url.as_str())
+            .send()
+            .map_err(|err| Box::new(err) as Box<Error>)
+            .and_then(|mut response| {
+                if response.status().as_u16() != 200 {
+                    return Either::A(future::ok(false));
+                }
+                Either::B(
+                    response
+                        .json()
+                        .map_err(|err| Box::new(err) as Box<Error>)
+                        .map(|info: TokenInfoResponse| info.active),
+                )
+            })
+    }
+
+    /// Revoke `self.access_token`, invalidating it so it can no longer be used. Takes `self` by
+    /// value (rather than `&mut self`), consistent with `get_appointments`, and hands it back in
+    /// the resolved future with `expires_at` updated.
+    pub fn revoke_token(self) -> impl Future<Item = Self, Error = Box<Error>> {
+        let url = format!(
+            "{}?access_token={}",
+            self.client.url(&self.school, "/oauth/logout"),
+            self.access_token
+        );
+
+        self.client
+            .inner
+            .post(url.as_str())
+            .send()
+            .map_err(|err| Box::new(err) as Box<Error>)
+            .and_then(|response| {
+                if response.status().as_u16() != 200 {
+                    return Err(Box::new(ScheduleError("response code is not 200".to_string())) as Box<Error>);
+                }
+                Ok(response)
+            })
+            .map(move |_| {
+                let mut schedule = self;
+                schedule.expires_at = Some(SystemTime::now());
+                schedule
+            })
+    }
+
+    /// Get the appointments between `start` and `end` from the API, and set them to
+    /// `self.appointments`. The API only returns one page of rows per request, so this pages
+    /// through `startRow`/`endRow` (in steps of the `AsyncScheduleClient`'s configured page
+    /// size) until the server reports `endRow >= totalRows`, concatenating every page's
+    /// appointments before the final sort by start time. `self.total_rows` is set to the
+    /// server-reported total. Retries each page's request according to the
+    /// `AsyncScheduleClient`'s configured retry count before giving up.
+    ///
+    /// Takes `self` by value (rather than `&mut self`) and hands it back in the resolved
+    /// future, so the future it returns owns everything it needs and isn't tied to the lifetime
+    /// of a borrow — that's what lets `ChangeStream` drive it across `poll` calls.
+    /// Returns a future resolving to `Self`, or an error.
+    pub fn get_appointments(
+        self,
+        start: i64,
+        end: i64,
+    ) -> impl Future<Item = Self, Error = Box<Error>> {
+        let page_size = i64::from(self.client.page_size());
+
+        future::loop_fn(
+            (self, Vec::new(), 0i64),
+            move |(schedule, mut appointments, row)| {
+                let url = format!(
+                    "{}?user=~me&start={}&end={}&startRow={}&endRow={}&access_token={}",
+                    schedule.client.url(&schedule.school, "/api/v3/appointments"),
+                    start,
+                    end,
+                    row,
+                    row + page_size,
+                    schedule.access_token
+                );
+
+                fetch_appointments_page(schedule.client.clone(), url).and_then(move |page| {
+                    // The HTTP status is usually 200 even when Zermelo reports an error in the
+                    // JSON envelope itself, so check the envelope's own status too.
+                    if page.status != 200 {
+                        return Err(Box::new(ScheduleError(format!(
+                            "Zermelo reported status {}: {}",
+                            page.status, page.message
+                        ))) as Box<Error>);
+                    }
+
+                    appointments.extend(page.data);
+                    let mut schedule = schedule;
+                    schedule.total_rows = Some(page.total_rows);
+
+                    if page.end_row >= page.total_rows {
+                        Ok(Loop::Break((schedule, appointments)))
+                    } else if page.end_row <= row {
+                        Err(Box::new(ScheduleError(format!(
+                            "Zermelo's endRow ({}) did not advance past the requested row ({}); \
+                             refusing to loop forever",
+                            page.end_row, row
+                        ))) as Box<Error>)
+                    } else {
+                        Ok(Loop::Continue((schedule, appointments, page.end_row)))
+                    }
+                })
+            },
+        )
+        .map(|(mut schedule, mut appointments)| {
+            appointments.sort_unstable_by_key(|k| k.start.unwrap_or(0));
+            schedule.appointments = appointments;
+            schedule
+        })
+    }
+
+    /// Turn this `AsyncSchedule` into a [`ChangeStream`](../stream/struct.ChangeStream.html)
+    /// that polls the API according to `config` and yields a `ScheduleDiff` whenever the
+    /// schedule changes.
+    pub fn changes(self, config: ChangeStreamConfig) -> ChangeStream {
+        ChangeStream::new(self, config)
+    }
+}
+
+/// Fetch a single page of appointments from `url`, retrying according to `client`'s configured
+/// retry count before giving up. Mirrors `Schedule::fetch_appointments_body`/
+/// `fetch_appointments_body_once`, but as a loop over a future instead of a blocking loop, since
+/// futures 0.1 has no `?`/blocking retry primitive to share between the two.
+fn fetch_appointments_page(
+    client: AsyncScheduleClient,
+    url: String,
+) -> impl Future<Item = AppointmentsResponseResponse, Error = Box<Error>> {
+    let attempts = client.retries() + 1;
+
+    future::loop_fn(attempts, move |attempts_left| {
+        client
+            .inner
+            .get(url.as_str())
+            .send()
+            .map_err(|err| Box::new(err) as Box<Error>)
+            .and_then(|response| {
+                if response.status().as_u16() != 200 {
+                    return Err(Box::new(ScheduleError("response code is not 200".to_string())) as Box<Error>);
+                }
+                Ok(response)
+            })
+            .and_then(|mut response| {
+                response
+                    .json::<AppointmentsResponse>()
+                    .map_err(|err| Box::new(err) as Box<Error>)
+            })
+            .then(move |result| match result {
+                Ok(response) => Ok(Loop::Break(response.response)),
+                Err(err) => {
+                    if attempts_left <= 1 {
+                        Err(err)
+                    } else {
+                        Ok(Loop::Continue(attempts_left - 1))
+                    }
+                }
+            })
+    })
+}
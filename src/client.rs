@@ -0,0 +1,279 @@
+//! A configurable, reusable HTTP client used to talk to a Zermelo portal.
+//!
+//! Building a fresh `reqwest::Client` on every request (as `Schedule` originally did) throws
+//! away connection pooling and leaves no room to configure timeouts, a user-agent, a proxy, or
+//! a custom base URL (useful for self-hosted portals, staging environments, and tests). A
+//! `ScheduleClient`, produced by `ScheduleClientBuilder`, fixes that by being built once and
+//! shared by every request a `Schedule` makes.
+
+use std::error::Error;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, USER_AGENT};
+use reqwest::r#async::Client as AsyncClient;
+use reqwest::{Client, Proxy};
+
+use schedule::ScheduleError;
+
+const DEFAULT_USER_AGENT: &str = "zermelo-rs";
+
+/// The default number of rows requested per page when fetching appointments.
+const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// A reusable, configurable HTTP client for talking to a Zermelo portal.
+///
+/// Construct one with [`ScheduleClientBuilder`](struct.ScheduleClientBuilder.html) and share it
+/// across `Schedule` instances instead of letting each request build its own `reqwest::Client`.
+#[derive(Clone)]
+pub struct ScheduleClient {
+    pub(crate) inner: Client,
+    pub(crate) base_url: Option<String>,
+    pub(crate) retries: u32,
+    pub(crate) page_size: u32,
+}
+
+impl ScheduleClient {
+    /// Start building a `ScheduleClient` with the library's defaults (a `5` second timeout, an
+    /// `Accept: application/json` header, and the `zermelo-rs` user-agent).
+    pub fn builder() -> ScheduleClientBuilder {
+        ScheduleClientBuilder::default()
+    }
+
+    /// Build the full URL for `path` (e.g. `"/api/v3/oauth/token"`) against `school`, honouring
+    /// a custom base URL if one was configured.
+    pub(crate) fn url(&self, school: &str, path: &str) -> String {
+        match self.base_url {
+            Some(ref base_url) => format!("{}{}", base_url.trim_end_matches('/'), path),
+            None => format!("https://{}.zportal.nl{}", school, path),
+        }
+    }
+
+    /// The number of times a failed request should be retried, as configured on the builder.
+    pub(crate) fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// The number of rows requested per page when fetching appointments.
+    pub(crate) fn page_size(&self) -> u32 {
+        self.page_size
+    }
+}
+
+/// The async counterpart to [`ScheduleClient`](struct.ScheduleClient.html), used by
+/// [`AsyncSchedule`](../async_schedule/struct.AsyncSchedule.html) so the async API gets the same
+/// configured timeout, headers, proxy, base URL, retries, and page size as the blocking one,
+/// instead of falling back to an unconfigured `reqwest::r#async::Client::new()`.
+///
+/// `Clone` is cheap: `reqwest::r#async::Client` is `Arc`-backed internally.
+#[derive(Clone)]
+pub struct AsyncScheduleClient {
+    pub(crate) inner: AsyncClient,
+    pub(crate) base_url: Option<String>,
+    pub(crate) retries: u32,
+    pub(crate) page_size: u32,
+}
+
+impl AsyncScheduleClient {
+    /// Build the full URL for `path` against `school`, honouring a custom base URL if one was
+    /// configured.
+    pub(crate) fn url(&self, school: &str, path: &str) -> String {
+        match self.base_url {
+            Some(ref base_url) => format!("{}{}", base_url.trim_end_matches('/'), path),
+            None => format!("https://{}.zportal.nl{}", school, path),
+        }
+    }
+
+    /// The number of times a failed request should be retried, as configured on the builder.
+    pub(crate) fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// The number of rows requested per page when fetching appointments.
+    pub(crate) fn page_size(&self) -> u32 {
+        self.page_size
+    }
+}
+
+/// A builder for [`ScheduleClient`](struct.ScheduleClient.html).
+pub struct ScheduleClientBuilder {
+    base_url: Option<String>,
+    timeout: Option<Duration>,
+    proxy: Option<Proxy>,
+    headers: HeaderMap,
+    retries: u32,
+    page_size: u32,
+}
+
+impl Default for ScheduleClientBuilder {
+    fn default() -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
+
+        ScheduleClientBuilder {
+            base_url: None,
+            timeout: Some(Duration::from_secs(5)),
+            proxy: None,
+            headers,
+            retries: 0,
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+impl ScheduleClientBuilder {
+    /// Override the base URL (e.g. `"https://my-school.example.com"`) instead of the default
+    /// `https://{school}.zportal.nl`. Useful for self-hosted portals, staging, and tests.
+    pub fn base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Set the request timeout. Defaults to `5` seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Disable the request timeout entirely.
+    pub fn no_timeout(mut self) -> Self {
+        self.timeout = None;
+        self
+    }
+
+    /// Route every request through `proxy`.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Add a default header, sent with every request.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Set the number of times a failed request is retried before giving up. Defaults to `0`.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set the number of rows requested per page when fetching appointments. Defaults to `100`.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Build the `ScheduleClient`. Fails if the underlying `reqwest::Client` fails to build,
+    /// e.g. because of an invalid proxy or TLS backend error, or if `page_size` was set to `0`
+    /// (with which `Schedule::get_appointments` would request empty row windows and never
+    /// advance its pagination).
+    pub fn build(self) -> Result<ScheduleClient, Box<Error>> {
+        if self.page_size == 0 {
+            return Err(Box::new(ScheduleError("page_size must be greater than 0".to_string())));
+        }
+
+        let mut builder = Client::builder().default_headers(self.headers);
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            builder = builder.proxy(proxy);
+        }
+
+        let inner = builder.build()?;
+
+        Ok(ScheduleClient {
+            inner,
+            base_url: self.base_url,
+            retries: self.retries,
+            page_size: self.page_size,
+        })
+    }
+
+    /// Build the [`AsyncScheduleClient`](struct.AsyncScheduleClient.html) counterpart, from the
+    /// same configuration. Fails the same way `build` does.
+    pub fn build_async(self) -> Result<AsyncScheduleClient, Box<Error>> {
+        if self.page_size == 0 {
+            return Err(Box::new(ScheduleError("page_size must be greater than 0".to_string())));
+        }
+
+        let mut builder = AsyncClient::builder().default_headers(self.headers);
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            builder = builder.proxy(proxy);
+        }
+
+        let inner = builder.build()?;
+
+        Ok(AsyncScheduleClient {
+            inner,
+            base_url: self.base_url,
+            retries: self.retries,
+            page_size: self.page_size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use client::*;
+
+    #[test]
+    fn defaults() {
+        let client = ScheduleClient::builder().build().unwrap();
+        assert_eq!(client.retries(), 0);
+        assert_eq!(client.page_size(), DEFAULT_PAGE_SIZE);
+        assert_eq!(client.url("example", "/api/v3/oauth/token"), "https://example.zportal.nl/api/v3/oauth/token");
+    }
+
+    #[test]
+    fn retries_and_page_size_are_configurable() {
+        let client = ScheduleClient::builder()
+            .retries(3)
+            .page_size(50)
+            .build()
+            .unwrap();
+        assert_eq!(client.retries(), 3);
+        assert_eq!(client.page_size(), 50);
+    }
+
+    #[test]
+    fn base_url_overrides_the_default_host() {
+        let client = ScheduleClient::builder()
+            .base_url("https://example.com")
+            .build()
+            .unwrap();
+        assert_eq!(client.url("school", "/api/v3/oauth/token"), "https://example.com/api/v3/oauth/token");
+    }
+
+    #[test]
+    fn zero_page_size_is_rejected() {
+        let result = ScheduleClient::builder().page_size(0).build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn build_async_carries_the_same_configuration_as_build() {
+        let client = ScheduleClient::builder()
+            .base_url("https://example.com")
+            .retries(3)
+            .page_size(50)
+            .build_async()
+            .unwrap();
+        assert_eq!(client.retries(), 3);
+        assert_eq!(client.page_size(), 50);
+        assert_eq!(client.url("school", "/api/v3/oauth/token"), "https://example.com/api/v3/oauth/token");
+    }
+
+    #[test]
+    fn build_async_rejects_zero_page_size_too() {
+        let result = ScheduleClient::builder().page_size(0).build_async();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Diffing between two snapshots of a schedule, classifying what changed.
+
+use appointment::Appointment;
+
+/// The classification of a change between two snapshots of the same appointment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppointmentChange {
+    /// The appointment is present in the new snapshot but was not in the old one.
+    Added,
+    /// The appointment was present in the old snapshot but is no longer present in the new one.
+    Removed,
+    /// The appointment has been cancelled.
+    Cancelled,
+    /// The appointment's start/end time or time slot changed.
+    Moved,
+    /// Some other tracked field (subjects, teachers, locations, remark, ...) changed.
+    Modified,
+}
+
+/// A single changed appointment, paired with its classification and, if present, Zermelo's own
+/// description of the change.
+#[derive(Debug, Clone)]
+pub struct AppointmentChangeEntry {
+    /// The appointment as it appears in the snapshot the change was observed in (the new
+    /// snapshot for everything except `Removed`, where it is the old one).
+    pub appointment: Appointment,
+    /// The kind of change that was detected.
+    pub change: AppointmentChange,
+    /// Zermelo's own description of the change, if any.
+    pub change_description: Option<String>,
+}
+
+/// The result of diffing two schedule snapshots, grouped by kind of change.
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleDiff {
+    /// Appointments that are new since the previous snapshot.
+    pub added: Vec<AppointmentChangeEntry>,
+    /// Appointments that were in the previous snapshot but are gone from this one.
+    pub removed: Vec<AppointmentChangeEntry>,
+    /// Appointments that have been cancelled.
+    pub cancelled: Vec<AppointmentChangeEntry>,
+    /// Appointments whose start/end time or time slot changed.
+    pub moved: Vec<AppointmentChangeEntry>,
+    /// Appointments with some other change (subjects, teachers, locations, remark, ...).
+    pub modified: Vec<AppointmentChangeEntry>,
+}
+
+impl ScheduleDiff {
+    /// Whether anything changed between the two snapshots.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.cancelled.is_empty()
+            && self.moved.is_empty()
+            && self.modified.is_empty()
+    }
+
+    /// Diff `current` against `previous`, matching appointments by `appointment_instance`,
+    /// falling back to `id`, falling back to a composite key of `(start, subjects, groups)` for
+    /// appointments the API returns without any id at all.
+    pub(crate) fn compute(current: &[Appointment], previous: &[Appointment]) -> Self {
+        let mut diff = ScheduleDiff::default();
+
+        for appointment in current {
+            match find_match(appointment, previous) {
+                Some(previous_appointment) => {
+                    if let Some(change) = classify_change(previous_appointment, appointment) {
+                        push(&mut diff, change, appointment.clone());
+                    }
+                }
+                None => push(&mut diff, AppointmentChange::Added, appointment.clone()),
+            }
+        }
+
+        for appointment in previous {
+            if find_match(appointment, current).is_none() {
+                push(&mut diff, AppointmentChange::Removed, appointment.clone());
+            }
+        }
+
+        diff
+    }
+}
+
+fn push(diff: &mut ScheduleDiff, change: AppointmentChange, appointment: Appointment) {
+    let entry = AppointmentChangeEntry {
+        change_description: appointment.change_description.clone(),
+        appointment,
+        change,
+    };
+    match change {
+        AppointmentChange::Added => diff.added.push(entry),
+        AppointmentChange::Removed => diff.removed.push(entry),
+        AppointmentChange::Cancelled => diff.cancelled.push(entry),
+        AppointmentChange::Moved => diff.moved.push(entry),
+        AppointmentChange::Modified => diff.modified.push(entry),
+    }
+}
+
+/// A key identifying the same logical appointment across two snapshots.
+#[derive(PartialEq, Eq)]
+enum MatchKey<'a> {
+    Instance(i64),
+    Id(i64),
+    Composite(Option<i64>, &'a [String], &'a [String]),
+}
+
+fn match_key(appointment: &Appointment) -> MatchKey<'_> {
+    if let Some(instance) = appointment.appointment_instance {
+        return MatchKey::Instance(instance);
+    }
+    if let Some(id) = appointment.id {
+        return MatchKey::Id(id);
+    }
+    MatchKey::Composite(
+        appointment.start,
+        appointment.subjects.as_ref().map_or(&[], Vec::as_slice),
+        appointment.groups.as_ref().map_or(&[], Vec::as_slice),
+    )
+}
+
+fn find_match<'a>(appointment: &Appointment, others: &'a [Appointment]) -> Option<&'a Appointment> {
+    let key = match_key(appointment);
+    others.iter().find(|other| match_key(other) == key)
+}
+
+/// Classify what changed about an appointment between `previous` and `current`, or `None` if
+/// nothing relevant changed.
+fn classify_change(previous: &Appointment, current: &Appointment) -> Option<AppointmentChange> {
+    // A cancellation takes priority over any other classification.
+    if current.cancelled == Some(true) {
+        if previous.cancelled == Some(true) && previous == current {
+            return None;
+        }
+        return Some(AppointmentChange::Cancelled);
+    }
+
+    if previous.start != current.start
+        || previous.end != current.end
+        || previous.start_time_slot != current.start_time_slot
+        || previous.end_time_slot != current.end_time_slot
+    {
+        return Some(AppointmentChange::Moved);
+    }
+
+    if previous.cancelled != current.cancelled
+        || previous.subjects != current.subjects
+        || previous.teachers != current.teachers
+        || previous.groups != current.groups
+        || previous.locations != current.locations
+        || previous.remark != current.remark
+    {
+        return Some(AppointmentChange::Modified);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn appointment(id: i64, cancelled: Option<bool>) -> Appointment {
+        Appointment {
+            id: Some(id),
+            appointment_instance: None,
+            start: Some(100),
+            end: Some(200),
+            start_time_slot: Some(1),
+            end_time_slot: Some(1),
+            subjects: Some(vec![String::from("ne")]),
+            teachers: Some(vec![String::from("KRO")]),
+            groups: Some(vec![String::from("v1a")]),
+            locations: Some(vec![String::from("M92")]),
+            appointment_type: Some(String::from("lesson")),
+            remark: None,
+            valid: Some(true),
+            cancelled,
+            modified: Some(false),
+            moved: Some(false),
+            new: Some(false),
+            change_description: None,
+            last_modified: None,
+            branch_of_school: None,
+        }
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let previous = vec![appointment(1, Some(false))];
+        let current = vec![appointment(2, Some(false))];
+
+        let diff = ScheduleDiff::compute(&current, &previous);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.removed.len(), 1);
+        assert!(diff.cancelled.is_empty());
+        assert!(diff.moved.is_empty());
+        assert!(diff.modified.is_empty());
+    }
+
+    #[test]
+    fn cancellation_is_detected() {
+        let previous = vec![appointment(1, Some(false))];
+        let current = vec![appointment(1, Some(true))];
+
+        let diff = ScheduleDiff::compute(&current, &previous);
+        assert_eq!(diff.cancelled.len(), 1);
+        assert!(diff.modified.is_empty());
+    }
+
+    #[test]
+    fn un_cancellation_is_reported_as_modified() {
+        let previous = vec![appointment(1, Some(true))];
+        let current = vec![appointment(1, Some(false))];
+
+        let diff = ScheduleDiff::compute(&current, &previous);
+        assert!(diff.cancelled.is_empty());
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].appointment.cancelled, Some(false));
+    }
+
+    #[test]
+    fn unchanged_appointment_produces_no_diff() {
+        let previous = vec![appointment(1, Some(false))];
+        let current = vec![appointment(1, Some(false))];
+
+        let diff = ScheduleDiff::compute(&current, &previous);
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn moved_takes_priority_over_modified() {
+        let previous = appointment(1, Some(false));
+        let mut current = appointment(1, Some(false));
+        current.start = Some(999);
+        current.remark = Some(String::from("new remark"));
+
+        let diff = ScheduleDiff::compute(&[current], &[previous]);
+        assert_eq!(diff.moved.len(), 1);
+        assert!(diff.modified.is_empty());
+    }
+}
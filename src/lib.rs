@@ -0,0 +1,27 @@
+//! A Rust wrapper for the [Zermelo](https://zermelo.nl) API.
+
+extern crate futures;
+extern crate lettre;
+extern crate lettre_email;
+extern crate reqwest;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate tokio_timer;
+
+pub mod appointment;
+pub mod async_schedule;
+pub mod client;
+pub mod diff;
+pub mod notify;
+pub mod schedule;
+pub mod stream;
+
+pub use appointment::Appointment;
+pub use async_schedule::AsyncSchedule;
+pub use client::{AsyncScheduleClient, ScheduleClient, ScheduleClientBuilder};
+pub use diff::{AppointmentChange, AppointmentChangeEntry, ScheduleDiff};
+pub use notify::{notify, NotifyConfig};
+pub use schedule::Schedule;
+pub use stream::{ChangeStream, ChangeStreamConfig};
@@ -0,0 +1,224 @@
+//! Optional email notifications for schedule changes, sent over SMTP via `lettre`.
+//!
+//! Given a [`ScheduleDiff`](../diff/struct.ScheduleDiff.html), [`notify`](fn.notify.html) renders
+//! a concise, human-readable summary of what changed and emails it, so a caller can run
+//! `get_appointments`/`diff` on a cron or poll loop and only hear about it when something
+//! actually changed.
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+
+use lettre::smtp::authentication::Credentials;
+use lettre::{SmtpClient, Transport};
+use lettre_email::EmailBuilder;
+
+use diff::{AppointmentChangeEntry, ScheduleDiff};
+use schedule::ScheduleError;
+
+/// SMTP configuration for [`notify`](fn.notify.html).
+///
+/// Any field left as `None` falls back to an environment variable when `notify` is called:
+/// `host` to `SMTP_HOST`, `user`/`password` to `SMTP_USER`/`SMTP_PASSWORD`, and `from`/`to` to
+/// `SMTP_FROM`/`SMTP_TO`.
+#[derive(Clone, Default)]
+pub struct NotifyConfig {
+    /// The SMTP host to connect to, e.g. `"smtp.example.com"`.
+    pub host: Option<String>,
+    /// The SMTP username. Falls back to the `SMTP_USER` environment variable.
+    pub user: Option<String>,
+    /// The SMTP password. Falls back to the `SMTP_PASSWORD` environment variable.
+    pub password: Option<String>,
+    /// The address the notification is sent from.
+    pub from: Option<String>,
+    /// The address the notification is sent to.
+    pub to: Option<String>,
+}
+
+// Hand-written so that `password` never ends up in a log line or bug report via `{:?}`.
+impl fmt::Debug for NotifyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NotifyConfig")
+            .field("host", &self.host)
+            .field("user", &self.user)
+            .field("password", &self.password.as_ref().map(|_| "[redacted]"))
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .finish()
+    }
+}
+
+impl NotifyConfig {
+    /// Build a `NotifyConfig` purely from the `SMTP_HOST`, `SMTP_USER`, `SMTP_PASSWORD`,
+    /// `SMTP_FROM`, and `SMTP_TO` environment variables.
+    pub fn from_env() -> Self {
+        NotifyConfig {
+            host: env::var("SMTP_HOST").ok(),
+            user: env::var("SMTP_USER").ok(),
+            password: env::var("SMTP_PASSWORD").ok(),
+            from: env::var("SMTP_FROM").ok(),
+            to: env::var("SMTP_TO").ok(),
+        }
+    }
+
+    fn resolve(field: Option<String>, env_var: &'static str, missing: &'static str) -> Result<String, Box<Error>> {
+        field
+            .or_else(|| env::var(env_var).ok())
+            .ok_or_else(|| Box::new(ScheduleError(missing.to_string())) as Box<Error>)
+    }
+}
+
+/// Render `diff` as a human-readable summary and email it per `config`.
+///
+/// Does nothing (and sends no mail) if `diff.is_empty()`.
+pub fn notify(diff: &ScheduleDiff, config: &NotifyConfig) -> Result<(), Box<Error>> {
+    if diff.is_empty() {
+        return Ok(());
+    }
+
+    let host = NotifyConfig::resolve(config.host.clone(), "SMTP_HOST", "SMTP_HOST is not configured")?;
+    let user = NotifyConfig::resolve(config.user.clone(), "SMTP_USER", "SMTP_USER is not configured")?;
+    let password = NotifyConfig::resolve(
+        config.password.clone(),
+        "SMTP_PASSWORD",
+        "SMTP_PASSWORD is not configured",
+    )?;
+    let from = NotifyConfig::resolve(config.from.clone(), "SMTP_FROM", "SMTP_FROM is not configured")?;
+    let to = NotifyConfig::resolve(config.to.clone(), "SMTP_TO", "SMTP_TO is not configured")?;
+
+    let email = EmailBuilder::new()
+        .to(to.as_str())
+        .from(from.as_str())
+        .subject("Your schedule has changed")
+        .text(render_summary(diff))
+        .build()?;
+
+    let mut transport = SmtpClient::new_simple(host.as_str())?
+        .credentials(Credentials::new(user, password))
+        .transport();
+
+    transport.send(email.into())?;
+
+    Ok(())
+}
+
+/// Render `diff` into the plain-text body of the notification email.
+fn render_summary(diff: &ScheduleDiff) -> String {
+    let mut summary = String::new();
+    render_section(&mut summary, "Cancelled", &diff.cancelled);
+    render_section(&mut summary, "Moved", &diff.moved);
+    render_section(&mut summary, "Modified", &diff.modified);
+    render_section(&mut summary, "Added", &diff.added);
+    render_section(&mut summary, "Removed", &diff.removed);
+    summary
+}
+
+fn render_section(summary: &mut String, title: &str, entries: &[AppointmentChangeEntry]) {
+    if entries.is_empty() {
+        return;
+    }
+
+    summary.push_str(title);
+    summary.push_str(":\n");
+    for entry in entries {
+        let subjects = entry
+            .appointment
+            .subjects
+            .as_ref()
+            .map(|subjects| subjects.join(", "))
+            .unwrap_or_else(|| "?".to_string());
+        let slot = entry
+            .appointment
+            .start_time_slot
+            .map(|slot| slot.to_string())
+            .unwrap_or_else(|| "?".to_string());
+
+        summary.push_str(&format!("  - {} (slot {})", subjects, slot));
+        if let Some(ref description) = entry.change_description {
+            summary.push_str(": ");
+            summary.push_str(description);
+        }
+        summary.push('\n');
+    }
+    summary.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use appointment::Appointment;
+    use diff::AppointmentChange;
+
+    fn entry(subjects: &str, slot: i64, change_description: Option<&str>) -> AppointmentChangeEntry {
+        AppointmentChangeEntry {
+            appointment: Appointment {
+                id: None,
+                appointment_instance: None,
+                start: None,
+                end: None,
+                start_time_slot: Some(slot),
+                end_time_slot: None,
+                subjects: Some(vec![subjects.to_string()]),
+                teachers: None,
+                groups: None,
+                locations: None,
+                appointment_type: None,
+                remark: None,
+                valid: None,
+                cancelled: None,
+                modified: None,
+                moved: None,
+                new: None,
+                change_description: change_description.map(|s| s.to_string()),
+                last_modified: None,
+                branch_of_school: None,
+            },
+            change: AppointmentChange::Cancelled,
+            change_description: change_description.map(|s| s.to_string()),
+        }
+    }
+
+    #[test]
+    fn render_section_skips_empty_entries() {
+        let mut summary = String::new();
+        render_section(&mut summary, "Cancelled", &[]);
+        assert!(summary.is_empty());
+    }
+
+    #[test]
+    fn render_section_includes_title_subjects_slot_and_description() {
+        let entries = vec![entry("ne", 3, Some("location changed"))];
+
+        let mut summary = String::new();
+        render_section(&mut summary, "Cancelled", &entries);
+
+        assert!(summary.starts_with("Cancelled:\n"));
+        assert!(summary.contains("ne (slot 3)"));
+        assert!(summary.contains("location changed"));
+    }
+
+    #[test]
+    fn render_section_omits_description_when_absent() {
+        let entries = vec![entry("wi", 1, None)];
+
+        let mut summary = String::new();
+        render_section(&mut summary, "Moved", &entries);
+
+        assert_eq!(summary, "Moved:\n  - wi (slot 1)\n\n");
+    }
+
+    #[test]
+    fn render_summary_only_includes_non_empty_sections() {
+        let diff = ScheduleDiff {
+            cancelled: vec![entry("ne", 3, None)],
+            ..Default::default()
+        };
+
+        let summary = render_summary(&diff);
+        assert!(summary.contains("Cancelled:"));
+        assert!(!summary.contains("Moved:"));
+        assert!(!summary.contains("Modified:"));
+        assert!(!summary.contains("Added:"));
+        assert!(!summary.contains("Removed:"));
+    }
+}
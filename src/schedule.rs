@@ -1,12 +1,14 @@
-use reqwest;
 use serde_json;
 use appointment::*;
+use client::ScheduleClient;
+use diff::ScheduleDiff;
 use std::io::Read;
 use std::error::Error;
 use std::fmt;
+use std::time::{Duration, SystemTime};
 
 #[derive(Debug)]
-pub struct ScheduleError(&'static str);
+pub struct ScheduleError(pub(crate) String);
 
 impl fmt::Display for ScheduleError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
@@ -16,7 +18,7 @@ impl fmt::Display for ScheduleError {
 
 impl Error for ScheduleError {
     fn description(&self) -> &str {
-        self.0
+        &self.0
     }
 }
 
@@ -29,49 +31,78 @@ pub struct Schedule {
     pub access_token: String,
     /// A vector of the appointments.
     pub appointments: Vec<Appointment>,
+    /// The total number of rows the API reported for the most recent `get_appointments` call,
+    /// or `None` if no call has been made yet.
+    pub total_rows: Option<i64>,
+    /// The token type reported alongside `access_token`, e.g. `"bearer"`. `None` if the access
+    /// token was supplied directly through `with_access_token` rather than obtained from the
+    /// API.
+    pub token_type: Option<String>,
+    /// The absolute time at which `access_token` expires, if known.
+    pub expires_at: Option<SystemTime>,
+    /// The (possibly shared) client used to talk to the school's portal.
+    client: ScheduleClient,
 }
 
 impl Schedule {
     /// Create a new `Schedule` from an authorization code (only once usable) and a school identifier.
-    /// This will get the access token from the API.
+    /// This will get the access token from the API, using a default `ScheduleClient`.
     /// Returns a `Schedule` or an error.
     pub fn new<S>(school: &S, code: &S) -> Result<Self, Box<Error>>
+    where
+        S: ToString,
+    {
+        Self::with_client(school, code, ScheduleClient::builder().build()?)
+    }
+
+    /// Create a new `Schedule` from an authorization code (only once usable), a school
+    /// identifier, and a pre-built `ScheduleClient`. This will get the access token from the
+    /// API. Returns a `Schedule` or an error.
+    pub fn with_client<S>(school: &S, code: &S, client: ScheduleClient) -> Result<Self, Box<Error>>
     where
         S: ToString,
     {
         let school = school.to_string();
         let code = code.to_string();
 
-        let url = format!("https://{}.zportal.nl/api/v3/oauth/token", school);
+        let url = client.url(&school, "/api/v3/oauth/token");
         // Remove spaces from code.
         let code = code.replace(" ", "");
-        let post_data = [("grant_type", "autorization_code"), ("code", code.as_str())];
+        let post_data = [("grant_type", "authorization_code"), ("code", code.as_str())];
 
         // Send request.
-        let mut response = reqwest::Client::new()
+        let mut response = client
+            .inner
             .post(url.as_str())
             .form(&post_data)
             .send()?;
 
         // Check whether response code equals "200 OK".
         if response.status().as_u16() != 200 {
-            return Err(Box::new(ScheduleError("response code is not 200")));
+            return Err(Box::new(ScheduleError("response code is not 200".to_string())));
         }
 
         // Parse response as JSON.
         let json: AuthenticationResponse = response.json()?;
 
         let access_token = json.access_token;
+        let expires_at = json
+            .expires_in
+            .map(|expires_in| SystemTime::now() + Duration::from_secs(expires_in));
 
         Ok(Schedule {
             school: school.to_owned(),
             access_token,
             appointments: Vec::new(),
+            total_rows: None,
+            token_type: json.token_type,
+            expires_at,
+            client,
         })
     }
 
-    /// Create a new `Schedule` when an access token has been obtained already.
-    /// This cannot fail, so this will not return a `Result`.
+    /// Create a new `Schedule` when an access token has been obtained already, using a default
+    /// `ScheduleClient`. This cannot fail, so this will not return a `Result`.
     pub fn with_access_token<S>(school: &S, access_token: &S) -> Self
     where
         S: ToString,
@@ -80,41 +111,113 @@ impl Schedule {
             school: school.to_string(),
             access_token: access_token.to_string(),
             appointments: Vec::new(),
+            total_rows: None,
+            token_type: None,
+            expires_at: None,
+            client: ScheduleClient::builder()
+                .build()
+                .expect("the default ScheduleClient is always valid"),
         }
     }
 
-    /// Get the appointments between `start` and `end` from the API, and set them to `self.appointments`.
-    /// Returns a reference to itself, or an error.
-    pub fn get_appointments(&mut self, start: i64, end: i64) -> Result<&Self, Box<Error>> {
+    /// Whether `self.access_token` has expired, based on the expiry reported when it was
+    /// obtained. Returns `false` if no expiry is known (e.g. the token was supplied directly
+    /// through `with_access_token`).
+    pub fn is_expired(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => SystemTime::now() >= expires_at,
+            None => false,
+        }
+    }
+
+    /// Ask the API whether `self.access_token` is still valid, via the token introspection
+    /// endpoint.
+    pub fn introspect_token(&self) -> Result<bool, Box<Error>> {
         let url = format!(
-            "https://{}.zportal.nl/api/v3/appointments?user=~me&start={}&end={}&access_token={}",
-            self.school, start, end, self.access_token
+            "{}?access_token={}",
+            self.client.url(&self.school, "/api/v3/oauth/token/info"),
+            self.access_token
         );
 
-        // Make request.
-        let mut response = reqwest::get(url.as_str())?;
+        let mut response = self.client.inner.get(url.as_str()).send()?;
+        if response.status().as_u16() != 200 {
+            return Ok(false);
+        }
 
-        // Check whether response code equals "200 OK".
+        let info: TokenInfoResponse = response.json()?;
+        Ok(info.active)
+    }
+
+    /// Revoke `self.access_token`, invalidating it so it can no longer be used.
+    pub fn revoke_token(&mut self) -> Result<(), Box<Error>> {
+        let url = format!(
+            "{}?access_token={}",
+            self.client.url(&self.school, "/oauth/logout"),
+            self.access_token
+        );
+
+        let response = self.client.inner.post(url.as_str()).send()?;
         if response.status().as_u16() != 200 {
-            return Err(Box::new(ScheduleError("response code is not 200")));
+            return Err(Box::new(ScheduleError("response code is not 200".to_string())));
         }
 
-        // Read body to string.
-        let mut body = String::new();
-        response.read_to_string(&mut body)?;
+        self.expires_at = Some(SystemTime::now());
+        Ok(())
+    }
+
+    /// Get the appointments between `start` and `end` from the API, and set them to
+    /// `self.appointments`. The API only returns one page of rows per request, so this pages
+    /// through `startRow`/`endRow` (in steps of the `ScheduleClient`'s configured page size)
+    /// until the server reports `endRow >= totalRows`, concatenating every page's appointments
+    /// before the final sort by start time. `self.total_rows` is set to the server-reported
+    /// total. Returns a reference to itself, or an error. Retries each page's request according
+    /// to the `ScheduleClient`'s configured retry count before giving up.
+    pub fn get_appointments(&mut self, start: i64, end: i64) -> Result<&Self, Box<Error>> {
+        let mut appointments = Vec::new();
+        let mut row = 0i64;
+        let page_size = i64::from(self.client.page_size());
 
-        // Replace camelCase index with snake_case index, so we can deserialize it easier.
-        let body = body.replace("appointmentInstance", "appointment_instance")
-            .replace("startTimeSlot", "start_time_slot")
-            .replace("endTimeSlot", "end_time_slot")
-            .replace("type", "appointment_type")
-            .replace("lastModified", "last_modified")
-            .replace("changeDescription", "change_description")
-            .replace("branchOfSchool", "branch_of_school");
+        loop {
+            let url = format!(
+                "{}?user=~me&start={}&end={}&startRow={}&endRow={}&access_token={}",
+                self.client.url(&self.school, "/api/v3/appointments"),
+                start,
+                end,
+                row,
+                row + page_size,
+                self.access_token
+            );
 
-        let response: AppointmentsResponse = serde_json::from_str(body.as_str())?;
+            let body = self.fetch_appointments_body(url.as_str())?;
+            let response: AppointmentsResponse = serde_json::from_str(&body)?;
+            let page = response.response;
+
+            // The HTTP status is usually 200 even when Zermelo reports an error in the JSON
+            // envelope itself, so check the envelope's own status too.
+            if page.status != 200 {
+                return Err(Box::new(ScheduleError(format!(
+                    "Zermelo reported status {}: {}",
+                    page.status, page.message
+                ))));
+            }
+
+            appointments.extend(page.data);
+            self.total_rows = Some(page.total_rows);
+
+            if page.end_row >= page.total_rows {
+                break;
+            }
+            if page.end_row <= row {
+                return Err(Box::new(ScheduleError(format!(
+                    "Zermelo's endRow ({}) did not advance past the requested row ({}); \
+                     refusing to loop forever",
+                    page.end_row, row
+                ))));
+            }
+            row = page.end_row;
+        }
 
-        self.appointments = response.response.data;
+        self.appointments = appointments;
 
         // Sort appointments by start time.
         self.appointments
@@ -122,28 +225,99 @@ impl Schedule {
 
         Ok(self)
     }
+
+    /// Diff `self.appointments` against `previous`, classifying every change (added, removed,
+    /// cancelled, moved, or otherwise modified) since that earlier snapshot.
+    pub fn diff(&self, previous: &[Appointment]) -> ScheduleDiff {
+        ScheduleDiff::compute(&self.appointments, previous)
+    }
+
+    /// Fetch and read the raw response body for `url`, making up to the `ScheduleClient`'s
+    /// configured retry count plus one attempts before returning the last error.
+    fn fetch_appointments_body(&self, url: &str) -> Result<String, Box<Error>> {
+        let mut attempts_left = self.client.retries() + 1;
+        loop {
+            attempts_left -= 1;
+            match self.fetch_appointments_body_once(url) {
+                Ok(body) => return Ok(body),
+                Err(err) => {
+                    if attempts_left == 0 {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Make a single, non-retried attempt at fetching and reading the raw response body for
+    /// `url`.
+    fn fetch_appointments_body_once(&self, url: &str) -> Result<String, Box<Error>> {
+        // Make request.
+        let mut response = self.client.inner.get(url).send()?;
+
+        // Check whether response code equals "200 OK".
+        if response.status().as_u16() != 200 {
+            return Err(Box::new(ScheduleError("response code is not 200".to_string())));
+        }
+
+        // Read body to string.
+        let mut body = String::new();
+        response.read_to_string(&mut body)?;
+
+        Ok(body)
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct AuthenticationResponse {
+    pub(crate) access_token: String,
+    pub(crate) token_type: Option<String>,
+    pub(crate) expires_in: Option<u64>,
 }
 
 #[derive(Deserialize)]
-struct AuthenticationResponse {
-    access_token: String,
+pub(crate) struct TokenInfoResponse {
+    pub(crate) active: bool,
 }
 
 #[derive(Deserialize)]
-struct AppointmentsResponse {
-    response: AppointmentsResponseResponse,
+pub(crate) struct AppointmentsResponse {
+    pub(crate) response: AppointmentsResponseResponse,
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 // Why, Zermelo, would you wrap everything in a "response" map?
-struct AppointmentsResponseResponse {
-    data: Vec<Appointment>,
+pub(crate) struct AppointmentsResponseResponse {
+    pub(crate) status: i32,
+    pub(crate) message: String,
+    pub(crate) end_row: i64,
+    pub(crate) total_rows: i64,
+    pub(crate) data: Vec<Appointment>,
 }
 
 #[cfg(test)]
 mod tests {
     use serde_json;
     use schedule::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn is_expired_with_no_expiry_is_false() {
+        let schedule = Schedule::with_access_token(&"example", &"token");
+        assert!(!schedule.is_expired());
+    }
+
+    #[test]
+    fn is_expired_reflects_expires_at() {
+        let mut schedule = Schedule::with_access_token(&"example", &"token");
+
+        schedule.expires_at = Some(SystemTime::now() + Duration::from_secs(60));
+        assert!(!schedule.is_expired());
+
+        schedule.expires_at = Some(SystemTime::now() - Duration::from_secs(60));
+        assert!(schedule.is_expired());
+    }
 
     #[test]
     fn parse_request() {
@@ -179,15 +353,7 @@ mod tests {
             }
         }"#;
 
-        let json = json.replace("appointmentInstance", "appointment_instance")
-            .replace("startTimeSlot", "start_time_slot")
-            .replace("endTimeSlot", "end_time_slot")
-            .replace("type", "appointment_type")
-            .replace("lastModified", "lastModified")
-            .replace("changeDescription", "change_description")
-            .replace("branchOfSchool", "branch_of_school");
-
-        let response: AppointmentsResponse = serde_json::from_str(json.as_str()).unwrap();
+        let response: AppointmentsResponse = serde_json::from_str(json).unwrap();
         let appointment = &response.response.data[0];
         assert_eq!(appointment.id, Some(5));
         assert_eq!(appointment.start, Some(42364236));
@@ -0,0 +1,185 @@
+//! A polling-based, `Stream`-shaped live feed of schedule changes.
+//!
+//! Zermelo only offers a request/response API, not a push/subscribe one, so this recasts the
+//! fetch-then-diff loop as a `Stream` by re-fetching on an interval, diffing against the last
+//! snapshot, and only yielding when something actually changed.
+
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Poll, Stream};
+use tokio_timer::Interval;
+
+use appointment::Appointment;
+use async_schedule::AsyncSchedule;
+use diff::ScheduleDiff;
+
+/// Configuration for a [`ChangeStream`](struct.ChangeStream.html).
+pub struct ChangeStreamConfig {
+    /// How often to re-fetch the schedule and check for changes.
+    pub interval: Duration,
+    /// The start of the date window to fetch, as a Unix timestamp.
+    pub start: i64,
+    /// The end of the date window to fetch, as a Unix timestamp.
+    pub end: i64,
+    /// How long to wait before the next poll after a transient error, instead of the normal
+    /// `interval`.
+    pub backoff: Duration,
+}
+
+enum State {
+    Waiting,
+    // Drives an owned clone of `schedule` (see `AsyncSchedule`'s `Clone` impl), so the future is
+    // `'static` and isn't tied to a borrow of `self` across `poll` calls.
+    Fetching(Box<Future<Item = AsyncSchedule, Error = Box<Error>>>),
+}
+
+/// A live stream of `ScheduleDiff`s, produced by polling the API on an interval and only
+/// yielding an item when something changed since the previous poll.
+///
+/// Transient HTTP errors are yielded as `Err` items (so the stream keeps running and the
+/// consumer decides how to react) rather than ending the stream.
+pub struct ChangeStream {
+    schedule: AsyncSchedule,
+    config: ChangeStreamConfig,
+    ticker: Interval,
+    previous: Vec<Appointment>,
+    state: State,
+}
+
+impl ChangeStream {
+    /// Create a new `ChangeStream` that polls `schedule` for changes according to `config`.
+    pub fn new(schedule: AsyncSchedule, config: ChangeStreamConfig) -> Self {
+        let ticker = Interval::new(Instant::now(), config.interval);
+        ChangeStream {
+            schedule,
+            config,
+            ticker,
+            previous: Vec::new(),
+            state: State::Waiting,
+        }
+    }
+
+    fn back_off(&mut self) {
+        let deadline = backoff_deadline(Instant::now(), self.config.backoff);
+        self.ticker = Interval::new(deadline, self.config.interval);
+    }
+}
+
+/// When the next tick should fire after a transient error, given the current time and the
+/// configured backoff. Split out from `back_off` so it can be tested without a real clock.
+fn backoff_deadline(now: Instant, backoff: Duration) -> Instant {
+    now + backoff
+}
+
+/// Diff `current` against `previous`, but only return it when something actually changed.
+/// Split out from `poll`'s `State::Fetching` arm so the "only yield on a non-empty diff" rule
+/// can be tested without driving a real `Future`/`Stream`.
+fn diff_if_changed(current: &[Appointment], previous: &[Appointment]) -> Option<ScheduleDiff> {
+    let diff = ScheduleDiff::compute(current, previous);
+    if diff.is_empty() {
+        None
+    } else {
+        Some(diff)
+    }
+}
+
+impl Stream for ChangeStream {
+    type Item = Result<ScheduleDiff, Box<Error>>;
+    type Error = Box<Error>;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            match self.state {
+                State::Waiting => match self.ticker.poll() {
+                    Ok(Async::Ready(Some(_))) => {
+                        // Clone `schedule` (an `Arc`-backed client, its `String` fields and the
+                        // `appointments` vector it was created with) and move the clone into the
+                        // future, so it owns everything it needs instead of borrowing from `self`.
+                        let fetch = self.schedule
+                            .clone()
+                            .get_appointments(self.config.start, self.config.end);
+                        self.state = State::Fetching(Box::new(fetch));
+                    }
+                    Ok(Async::Ready(None)) => return Ok(Async::Ready(None)),
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(err) => {
+                        self.back_off();
+                        return Ok(Async::Ready(Some(Err(Box::new(err)))));
+                    }
+                },
+                State::Fetching(ref mut fetch) => match fetch.poll() {
+                    Ok(Async::Ready(schedule)) => {
+                        let appointments = schedule.appointments;
+                        let diff = diff_if_changed(&appointments, &self.previous);
+                        self.previous = appointments;
+                        self.state = State::Waiting;
+                        if let Some(diff) = diff {
+                            return Ok(Async::Ready(Some(Ok(diff))));
+                        }
+                    }
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(err) => {
+                        self.state = State::Waiting;
+                        self.back_off();
+                        return Ok(Async::Ready(Some(Err(err))));
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn appointment(id: i64) -> Appointment {
+        Appointment {
+            id: Some(id),
+            appointment_instance: None,
+            start: None,
+            end: None,
+            start_time_slot: None,
+            end_time_slot: None,
+            subjects: None,
+            teachers: None,
+            groups: None,
+            locations: None,
+            appointment_type: None,
+            remark: None,
+            valid: None,
+            cancelled: None,
+            modified: None,
+            moved: None,
+            new: None,
+            change_description: None,
+            last_modified: None,
+            branch_of_school: None,
+        }
+    }
+
+    #[test]
+    fn backoff_deadline_is_after_now_by_the_configured_backoff() {
+        let now = Instant::now();
+        let backoff = Duration::from_secs(30);
+
+        assert_eq!(backoff_deadline(now, backoff), now + backoff);
+    }
+
+    #[test]
+    fn diff_if_changed_is_none_when_nothing_changed() {
+        let snapshot = vec![appointment(1)];
+
+        assert!(diff_if_changed(&snapshot, &snapshot).is_none());
+    }
+
+    #[test]
+    fn diff_if_changed_is_some_when_something_changed() {
+        let previous = vec![appointment(1)];
+        let current = vec![appointment(1), appointment(2)];
+
+        let diff = diff_if_changed(&current, &previous).expect("expected a non-empty diff");
+        assert_eq!(diff.added.len(), 1);
+    }
+}